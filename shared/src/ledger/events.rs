@@ -1,4 +1,5 @@
 //! Logic to do with events emitted by the ledger.
+pub mod filter;
 pub mod log;
 
 use std::collections::HashMap;
@@ -7,6 +8,8 @@ use std::fmt::{self, Display};
 use std::ops::{Index, IndexMut};
 
 use borsh::{BorshDeserialize, BorshSerialize};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use thiserror::Error;
 
 use crate::ledger::native_vp::governance::utils::ProposalEvent;
@@ -28,6 +31,12 @@ pub enum EventLevel {
     Tx,
 }
 
+/// The schema namespace every locally emitted [`Event`] is tagged with.
+pub const EVENT_STANDARD: &str = "namada";
+
+/// The newest event schema version this build knows how to consume.
+pub const SUPPORTED_EVENT_VERSION: u64 = 1;
+
 /// Custom events that can be queried from Tendermint
 /// using a websocket client
 #[derive(Clone, Debug, Eq, PartialEq, BorshSerialize, BorshDeserialize)]
@@ -37,6 +46,11 @@ pub struct Event {
     /// The level of the event - whether it relates to a block or an individual
     /// transaction.
     pub level: EventLevel,
+    /// The schema namespace this event belongs to, e.g. `"namada"`.
+    pub standard: String,
+    /// The schema version of this event's attributes, scoped to
+    /// `standard` and `event_type`.
+    pub version: u64,
     /// Key-value attributes of the event.
     pub attributes: HashMap<String, String>,
 }
@@ -54,6 +68,32 @@ pub enum EventType {
     Proposal,
 }
 
+impl EventType {
+    /// Parse an [`EventType`] back from its string representation, as
+    /// produced by [`Display`]. Anything that isn't one of our reserved
+    /// names is treated as an IBC event type, matching how
+    /// [`EventType::Ibc`] is rendered.
+    pub fn from_raw(raw: &str) -> Self {
+        match raw {
+            "accepted" => EventType::Accepted,
+            "applied" => EventType::Applied,
+            "proposal" => EventType::Proposal,
+            other => EventType::Ibc(other.to_string()),
+        }
+    }
+
+    /// The schema version that events of this type are emitted with when
+    /// no other version is specified.
+    pub fn default_version(&self) -> u64 {
+        match self {
+            EventType::Accepted => 1,
+            EventType::Applied => 1,
+            EventType::Ibc(_) => 1,
+            EventType::Proposal => 1,
+        }
+    }
+}
+
 impl Display for EventType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -67,27 +107,33 @@ impl Display for EventType {
 }
 
 impl Event {
+    /// Creates a new, empty event of the given type and level, tagged with
+    /// the current [`EVENT_STANDARD`] and that event type's default
+    /// version.
+    pub fn new(event_type: EventType, level: EventLevel) -> Self {
+        let version = event_type.default_version();
+        Event {
+            event_type,
+            level,
+            standard: EVENT_STANDARD.to_string(),
+            version,
+            attributes: HashMap::new(),
+        }
+    }
+
     /// Creates a new event with the hash and height of the transaction
     /// already filled in
     #[cfg(feature = "ferveo-tpke")]
     pub fn new_tx_event(tx: &Tx, height: u64) -> Self {
         let mut event = match tx.header() {
             TxType::Wrapper(wrapper) => {
-                let mut event = Event {
-                    event_type: EventType::Accepted,
-                    level: EventLevel::Tx,
-                    attributes: HashMap::new(),
-                };
+                let mut event = Event::new(EventType::Accepted, EventLevel::Tx);
                 event["hash"] = tx.header_hash()
                 .to_string();
                 event
             }
             TxType::Decrypted(decrypted) => {
-                let mut event = Event {
-                    event_type: EventType::Applied,
-                    level: EventLevel::Tx,
-                    attributes: HashMap::new(),
-                };
+                let mut event = Event::new(EventType::Applied, EventLevel::Tx);
                 event["hash"] = tx
                     .clone()
                     .update_header(TxType::Raw(RawHeader::default()))
@@ -96,11 +142,7 @@ impl Event {
                 event
             }
             TxType::Protocol(_) => {
-                let mut event = Event {
-                    event_type: EventType::Applied,
-                    level: EventLevel::Tx,
-                    attributes: HashMap::new(),
-                };
+                let mut event = Event::new(EventType::Applied, EventLevel::Tx);
                 event["hash"] = tx.header_hash().to_string();
                 event
             }
@@ -123,6 +165,63 @@ impl Event {
     }
 }
 
+/// A struct whose fields make up the schema of an [`Event`], convertible
+/// to and from [`Event`] without hand-rolled `HashMap` bookkeeping.
+pub trait TypedEvent: Serialize + DeserializeOwned {
+    /// The [`EventType`] that instances of this struct are emitted as.
+    fn event_type() -> EventType;
+
+    /// The [`EventLevel`] that instances of this struct are emitted at.
+    fn event_level() -> EventLevel;
+
+    /// Turn `self` into an [`Event`], one [`EventAttribute`] per top-level
+    /// field.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` does not serialize to a JSON object.
+    fn into_event(&self) -> Event {
+        let value = serde_json::to_value(self)
+            .expect("a TypedEvent must serialize to valid JSON");
+        let object = match value {
+            serde_json::Value::Object(object) => object,
+            other => panic!(
+                "a TypedEvent must serialize to a JSON object, got: {}",
+                other
+            ),
+        };
+        let attributes = object
+            .into_iter()
+            .map(|(key, value)| {
+                let value = serde_json::to_string(&value)
+                    .expect("a JSON value must serialize to a string");
+                (key, value)
+            })
+            .collect();
+        Event {
+            attributes,
+            ..Event::new(Self::event_type(), Self::event_level())
+        }
+    }
+
+    /// Reconstruct a typed event from an [`Event`]'s attributes, which were
+    /// produced by [`TypedEvent::into_event`].
+    fn try_from_event(event: &Event) -> Result<Self, Error> {
+        let object = event
+            .attributes
+            .iter()
+            .map(|(key, value)| {
+                let value = serde_json::from_str(value).map_err(|e| {
+                    Error::Deserialize(key.clone(), e.to_string())
+                })?;
+                Ok((key.clone(), value))
+            })
+            .collect::<Result<_, Error>>()?;
+        serde_json::from_value(serde_json::Value::Object(object))
+            .map_err(|e| Error::Deserialize(event.event_type.to_string(), e.to_string()))
+    }
+}
+
 impl Index<&str> for Event {
     type Output = String;
 
@@ -142,10 +241,10 @@ impl IndexMut<&str> for Event {
 
 impl From<IbcEvent> for Event {
     fn from(ibc_event: IbcEvent) -> Self {
+        let event_type = EventType::Ibc(ibc_event.event_type);
         Self {
-            event_type: EventType::Ibc(ibc_event.event_type),
-            level: EventLevel::Tx,
             attributes: ibc_event.attributes,
+            ..Event::new(event_type, EventLevel::Tx)
         }
     }
 }
@@ -153,9 +252,8 @@ impl From<IbcEvent> for Event {
 impl From<ProposalEvent> for Event {
     fn from(proposal_event: ProposalEvent) -> Self {
         Self {
-            event_type: EventType::Proposal,
-            level: EventLevel::Block,
             attributes: proposal_event.attributes,
+            ..Event::new(EventType::Proposal, EventLevel::Block)
         }
     }
 }
@@ -163,17 +261,29 @@ impl From<ProposalEvent> for Event {
 /// Convert our custom event into the necessary tendermint proto type
 impl From<Event> for crate::tendermint_proto::abci::Event {
     fn from(event: Event) -> Self {
+        let mut attributes: Vec<EventAttribute> = event
+            .attributes
+            .into_iter()
+            .map(|(key, value)| EventAttribute {
+                key,
+                value,
+                index: true,
+            })
+            .collect();
+        // Reserved `standard`/`version` attributes.
+        attributes.push(EventAttribute {
+            key: "standard".to_string(),
+            value: event.standard,
+            index: true,
+        });
+        attributes.push(EventAttribute {
+            key: "version".to_string(),
+            value: event.version.to_string(),
+            index: true,
+        });
         Self {
             r#type: event.event_type.to_string(),
-            attributes: event
-                .attributes
-                .into_iter()
-                .map(|(key, value)| EventAttribute {
-                    key,
-                    value,
-                    index: true,
-                })
-                .collect(),
+            attributes,
         }
     }
 }
@@ -195,6 +305,214 @@ impl Attributes {
     }
 }
 
+/// The payload of a Tendermint subscription event, distinguishing a
+/// finalized block from a single applied transaction instead of making
+/// consumers re-derive the [`EventLevel`] from which attributes happen to
+/// be present.
+#[derive(Debug)]
+pub enum EventData {
+    /// A newly finalized block, together with the events it raised.
+    NewBlock {
+        /// The height of the finalized block.
+        height: u64,
+        /// The events raised while finalizing the block.
+        events: Vec<Event>,
+    },
+    /// The result of applying a single transaction.
+    Tx {
+        /// The height of the block the transaction was included in.
+        height: u64,
+        /// The hash of the transaction.
+        hash: String,
+        /// The Tendermint `DeliverTx` log.
+        log: String,
+        /// The events raised while applying the transaction.
+        events: Vec<Event>,
+    },
+}
+
+impl EventData {
+    /// Group a flat `"<event-type>.<attribute-key>" -> values` map (as
+    /// delivered in a subscription response's `events` field) back into
+    /// per-[`EventType`] [`Event`]s, rejecting any whose `standard`
+    /// attribute is [`EVENT_STANDARD`] and whose `version` attribute is
+    /// newer than [`SUPPORTED_EVENT_VERSION`].
+    fn events_from_map(
+        events: &HashMap<String, Vec<String>>,
+        level: EventLevel,
+    ) -> Result<Vec<Event>, Error> {
+        let mut grouped: HashMap<String, HashMap<String, String>> =
+            HashMap::new();
+        for (composite_key, values) in events {
+            let split = match composite_key.split_once('.') {
+                Some(split) => split,
+                None => continue,
+            };
+            let (event_type, attr_key) = split;
+            if matches!(event_type, "tm" | "tx" | "block") {
+                continue;
+            }
+            if let Some(value) = values.first() {
+                grouped
+                    .entry(event_type.to_string())
+                    .or_default()
+                    .insert(attr_key.to_string(), value.clone());
+            }
+        }
+        grouped
+            .into_iter()
+            .map(|(event_type, mut attributes)| {
+                let event_type = EventType::from_raw(&event_type);
+                // Only gate on `version` when `standard` marks this as one
+                // of our own events - foreign events (e.g. IBC
+                // channel-handshake events) may carry an unrelated
+                // `"version"` attribute of their own, like `"ics20-1"`.
+                let is_namada_standard =
+                    attributes.get("standard").map(String::as_str) == Some(EVENT_STANDARD);
+                let version = if is_namada_standard {
+                    match attributes.remove("version") {
+                        Some(version) => {
+                            let version: u64 = version.parse().map_err(|_| {
+                                Error::MissingValue("version".to_string())
+                            })?;
+                            if version > SUPPORTED_EVENT_VERSION {
+                                return Err(Error::UnsupportedVersion(version));
+                            }
+                            version
+                        }
+                        None => event_type.default_version(),
+                    }
+                } else {
+                    event_type.default_version()
+                };
+                let standard = attributes
+                    .remove("standard")
+                    .unwrap_or_else(|| EVENT_STANDARD.to_string());
+                Ok(Event {
+                    event_type,
+                    level: level.clone(),
+                    standard,
+                    version,
+                    attributes,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A subscription response delivered over the Tendermint event websocket,
+/// recording the query that produced it alongside the parsed event
+/// payload and the raw attributes it carried.
+#[derive(Debug)]
+pub struct SubscriptionEvent {
+    /// The subscription query string that produced this event.
+    pub query: String,
+    /// The parsed event payload.
+    pub data: EventData,
+    /// All attributes carried by the event, as `"<event-type>.<key>"` ->
+    /// (possibly several) values, reconstructed from the JSON.
+    pub events: HashMap<String, Vec<String>>,
+}
+
+impl SubscriptionEvent {
+    /// Map this subscription event back to the [`EventType`] it
+    /// corresponds to, whether it came from a finalized block or a single
+    /// transaction. Returns `None` when the event carries no
+    /// application-level attributes (only Tendermint's own `tm.*`, `tx.*`
+    /// and `block.*` keys). Any other event-type prefix is mapped with
+    /// [`EventType::from_raw`], so an event kind this client does not know
+    /// is reported as [`EventType::Ibc`] rather than as `None`.
+    pub fn event_type(&self) -> Option<EventType> {
+        if self.events.keys().any(|k| k.starts_with("applied.")) {
+            Some(EventType::Applied)
+        } else if self.events.keys().any(|k| k.starts_with("accepted.")) {
+            Some(EventType::Accepted)
+        } else {
+            self.events
+                .keys()
+                .filter_map(|k| k.split_once('.'))
+                .find(|(event_type, _)| {
+                    !matches!(*event_type, "tm" | "tx" | "block")
+                })
+                .map(|(event_type, _)| EventType::from_raw(event_type))
+        }
+    }
+}
+
+impl TryFrom<&serde_json::Value> for SubscriptionEvent {
+    type Error = Error;
+
+    fn try_from(json: &serde_json::Value) -> Result<Self, Self::Error> {
+        let query = json
+            .get("query")
+            .and_then(|q| q.as_str())
+            .ok_or(Error::MissingQuery)?
+            .to_string();
+        let data = json.get("data").ok_or(Error::MissingData)?;
+        let data_type = data
+            .get("type")
+            .and_then(|t| t.as_str())
+            .ok_or(Error::MissingData)?;
+        let value = data.get("value").ok_or(Error::MissingData)?;
+        let events: HashMap<String, Vec<String>> = json
+            .get("events")
+            .map(|events| serde_json::from_value(events.clone()))
+            .transpose()
+            .map_err(|_| Error::MissingEvents)?
+            .unwrap_or_default();
+
+        let data = match data_type {
+            "tendermint/event/NewBlock" => {
+                let height = value
+                    .get("block")
+                    .and_then(|b| b.get("header"))
+                    .and_then(|h| h.get("height"))
+                    .and_then(|h| h.as_str())
+                    .and_then(|h| h.parse().ok())
+                    .ok_or(Error::MissingData)?;
+                EventData::NewBlock {
+                    height,
+                    events: EventData::events_from_map(
+                        &events,
+                        EventLevel::Block,
+                    )?,
+                }
+            }
+            "tendermint/event/Tx" => {
+                let height = value
+                    .get("height")
+                    .and_then(|h| h.as_str())
+                    .and_then(|h| h.parse().ok())
+                    .ok_or(Error::MissingData)?;
+                let hash = value
+                    .get("hash")
+                    .and_then(|h| h.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                let log = value
+                    .get("log")
+                    .and_then(|l| l.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                EventData::Tx {
+                    height,
+                    hash,
+                    log,
+                    events: EventData::events_from_map(
+                        &events,
+                        EventLevel::Tx,
+                    )?,
+                }
+            }
+            other => {
+                return Err(Error::UnrecognizedDataType(other.to_string()))
+            }
+        };
+
+        Ok(SubscriptionEvent { query, data, events })
+    }
+}
+
 /// Errors to do with emitting events.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -207,6 +525,26 @@ pub enum Error {
     /// Missing value in attributes.
     #[error("Attributes missing value: {0}")]
     MissingValue(String),
+    /// Error deserializing a [`TypedEvent`]: the attribute key, or the
+    /// event type when the attributes do not form the struct, and the
+    /// error message.
+    #[error("Error deserializing `{0}`: {1}")]
+    Deserialize(String, String),
+    /// Missing `query` field on a subscription event.
+    #[error("Json missing `query` field")]
+    MissingQuery,
+    /// Missing or malformed `data` field on a subscription event.
+    #[error("Json missing or malformed `data` field")]
+    MissingData,
+    /// Malformed `events` field on a subscription event.
+    #[error("Json has a malformed `events` field")]
+    MissingEvents,
+    /// The subscription event's `data.type` was not recognized.
+    #[error("Unrecognized subscription event data type: {0}")]
+    UnrecognizedDataType(String),
+    /// The event's schema version is newer than this build understands.
+    #[error("Event version {0} is newer than the supported version ({SUPPORTED_EVENT_VERSION})")]
+    UnsupportedVersion(u64),
 }
 
 impl TryFrom<&serde_json::Value> for Attributes {
@@ -245,6 +583,197 @@ impl TryFrom<&serde_json::Value> for Attributes {
                 .unwrap(),
             );
         }
+
+        let is_namada_standard =
+            attributes.get("standard").map(String::as_str) == Some(EVENT_STANDARD);
+        if let Some(version) = is_namada_standard.then(|| attributes.get("version")).flatten() {
+            let version: u64 = version
+                .parse()
+                .map_err(|_| Error::MissingValue("version".to_string()))?;
+            if version > SUPPORTED_EVENT_VERSION {
+                return Err(Error::UnsupportedVersion(version));
+            }
+        }
+
         Ok(Attributes(attributes))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct MyTypedEvent {
+        hash: String,
+        height: u64,
+    }
+
+    impl TypedEvent for MyTypedEvent {
+        fn event_type() -> EventType {
+            EventType::Applied
+        }
+
+        fn event_level() -> EventLevel {
+            EventLevel::Tx
+        }
+    }
+
+    #[test]
+    fn typed_event_round_trips_through_event() {
+        let typed = MyTypedEvent {
+            hash: "deadbeef".to_string(),
+            height: 5,
+        };
+        let event = typed.into_event();
+        assert_eq!(event.event_type, EventType::Applied);
+        assert_eq!(event.level, EventLevel::Tx);
+        assert_eq!(event.get("hash"), Some(&"\"deadbeef\"".to_string()));
+        assert_eq!(event.get("height"), Some(&"5".to_string()));
+
+        let round_tripped = MyTypedEvent::try_from_event(&event).unwrap();
+        assert_eq!(round_tripped, typed);
+    }
+
+    #[test]
+    #[should_panic]
+    fn typed_event_panics_on_non_object() {
+        #[derive(Serialize, Deserialize)]
+        struct NotAnObject(u64);
+
+        impl TypedEvent for NotAnObject {
+            fn event_type() -> EventType {
+                EventType::Applied
+            }
+
+            fn event_level() -> EventLevel {
+                EventLevel::Tx
+            }
+        }
+
+        NotAnObject(1).into_event();
+    }
+
+    #[test]
+    fn subscription_event_parses_new_block() {
+        let json = serde_json::json!({
+            "query": "tm.event='NewBlock'",
+            "data": {
+                "type": "tendermint/event/NewBlock",
+                "value": {"block": {"header": {"height": "100"}}},
+            },
+            "events": {"proposal.proposal_id": ["5"]},
+        });
+        let subscription = SubscriptionEvent::try_from(&json).unwrap();
+        assert!(matches!(
+            subscription.data,
+            EventData::NewBlock { height: 100, .. }
+        ));
+        assert_eq!(subscription.event_type(), Some(EventType::Proposal));
+    }
+
+    #[test]
+    fn subscription_event_parses_tx() {
+        let json = serde_json::json!({
+            "query": "tm.event='Tx'",
+            "data": {
+                "type": "tendermint/event/Tx",
+                "value": {"height": "100", "hash": "abc", "log": "ok"},
+            },
+            "events": {"applied.hash": ["abc"]},
+        });
+        let subscription = SubscriptionEvent::try_from(&json).unwrap();
+        assert!(matches!(
+            subscription.data,
+            EventData::Tx { ref hash, .. } if hash == "abc"
+        ));
+        assert_eq!(subscription.event_type(), Some(EventType::Applied));
+    }
+
+    #[test]
+    fn subscription_event_rejects_future_version() {
+        let json = serde_json::json!({
+            "query": "tm.event='Tx'",
+            "data": {
+                "type": "tendermint/event/Tx",
+                "value": {"height": "100", "hash": "abc", "log": "ok"},
+            },
+            "events": {
+                "applied.hash": ["abc"],
+                "applied.standard": [EVENT_STANDARD.to_string()],
+                "applied.version": [(SUPPORTED_EVENT_VERSION + 1).to_string()],
+            },
+        });
+        assert!(matches!(
+            SubscriptionEvent::try_from(&json),
+            Err(Error::UnsupportedVersion(v)) if v == SUPPORTED_EVENT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn subscription_event_ignores_foreign_version_attribute() {
+        let json = serde_json::json!({
+            "query": "tm.event='Tx'",
+            "data": {
+                "type": "tendermint/event/Tx",
+                "value": {"height": "100", "hash": "abc", "log": "ok"},
+            },
+            "events": {
+                // An IBC channel-handshake event's own `version` (e.g. an
+                // ICS connection/channel version string), not our reserved
+                // schema version - must not be mistaken for one just
+                // because the key matches.
+                "channel_open_ack.version": ["ics20-1"],
+            },
+        });
+        let subscription = SubscriptionEvent::try_from(&json).unwrap();
+        let events = match subscription.data {
+            EventData::Tx { events, .. } => events,
+            _ => panic!("expected a Tx event"),
+        };
+        let event = &events[0];
+        assert_eq!(event.get("version"), Some(&"ics20-1".to_string()));
+        assert_eq!(event.standard, EVENT_STANDARD);
+        assert_eq!(event.version, event.event_type.default_version());
+    }
+
+    #[test]
+    fn subscription_event_rejects_unrecognized_data_type() {
+        let json = serde_json::json!({
+            "query": "tm.event='Tx'",
+            "data": {"type": "tendermint/event/SomethingElse", "value": {}},
+            "events": {},
+        });
+        assert!(matches!(
+            SubscriptionEvent::try_from(&json),
+            Err(Error::UnrecognizedDataType(_))
+        ));
+    }
+
+    #[test]
+    fn attributes_ignores_foreign_version_attribute() {
+        let json = serde_json::json!({
+            "attributes": [
+                {"key": "version", "value": "ics20-1"},
+            ],
+        });
+        let attrs = Attributes::try_from(&json).unwrap();
+        assert_eq!(attrs.get("version"), Some(&"ics20-1".to_string()));
+    }
+
+    #[test]
+    fn attributes_rejects_future_namada_version() {
+        let json = serde_json::json!({
+            "attributes": [
+                {"key": "standard", "value": EVENT_STANDARD},
+                {"key": "version", "value": (SUPPORTED_EVENT_VERSION + 1).to_string()},
+            ],
+        });
+        assert!(matches!(
+            Attributes::try_from(&json),
+            Err(Error::UnsupportedVersion(v)) if v == SUPPORTED_EVENT_VERSION + 1
+        ));
+    }
+}
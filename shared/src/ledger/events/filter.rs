@@ -0,0 +1,298 @@
+//! A declarative filter over ledger [`Event`]s, letting clients select the
+//! events they care about instead of manually matching on [`EventType`] and
+//! scanning `attributes` themselves.
+
+use super::{Event, EventLevel, EventType};
+
+/// A single predicate over an [`Event`]'s attributes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttrPredicate {
+    /// The attribute with the given key must equal the given value.
+    Eq(String, String),
+    /// The attribute with the given key must be present, with any value.
+    Present(String),
+    /// The attribute with the given key must contain the given substring.
+    Contains(String, String),
+}
+
+impl AttrPredicate {
+    /// Check whether `event` satisfies this predicate.
+    pub fn matches(&self, event: &Event) -> bool {
+        match self {
+            AttrPredicate::Eq(key, value) => {
+                event.get(key) == Some(value)
+            }
+            AttrPredicate::Present(key) => event.contains_key(key),
+            AttrPredicate::Contains(key, needle) => event
+                .get(key)
+                .map(|value| value.contains(needle.as_str()))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// A declarative filter selecting a subset of ledger events.
+///
+/// Build one with the `with_*` methods, then either check individual
+/// events with [`EventFilter::matches`] or render it into a Tendermint
+/// subscription query with [`EventFilter::to_query_string`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    event_types: Vec<EventType>,
+    level: Option<EventLevel>,
+    from_height: Option<u64>,
+    to_height: Option<u64>,
+    attrs: Vec<AttrPredicate>,
+}
+
+impl EventFilter {
+    /// Start building a filter that matches every event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match events whose [`EventType`] is `event_type`. Calling this
+    /// more than once matches any of the given types. An [`EventType::Ibc`]
+    /// matches by prefix against the event's IBC event type.
+    pub fn with_event_type(mut self, event_type: EventType) -> Self {
+        self.event_types.push(event_type);
+        self
+    }
+
+    /// Only match events at the given [`EventLevel`].
+    pub fn with_level(mut self, level: EventLevel) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Only match events whose `"height"` attribute is at least `height`.
+    pub fn from_height(mut self, height: u64) -> Self {
+        self.from_height = Some(height);
+        self
+    }
+
+    /// Only match events whose `"height"` attribute is at most `height`.
+    pub fn to_height(mut self, height: u64) -> Self {
+        self.to_height = Some(height);
+        self
+    }
+
+    /// Only match events whose attribute `key` is exactly `value`.
+    pub fn with_attr_eq(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.attrs
+            .push(AttrPredicate::Eq(key.into(), value.into()));
+        self
+    }
+
+    /// Only match events that carry the attribute `key`, with any value.
+    pub fn with_attr_present(mut self, key: impl Into<String>) -> Self {
+        self.attrs.push(AttrPredicate::Present(key.into()));
+        self
+    }
+
+    /// Only match events whose attribute `key` contains `needle` as a
+    /// substring.
+    pub fn with_attr_contains(
+        mut self,
+        key: impl Into<String>,
+        needle: impl Into<String>,
+    ) -> Self {
+        self.attrs
+            .push(AttrPredicate::Contains(key.into(), needle.into()));
+        self
+    }
+
+    /// Check whether `event` satisfies every predicate in this filter.
+    pub fn matches(&self, event: &Event) -> bool {
+        if !self.event_types.is_empty()
+            && !self.event_types.iter().any(|ty| Self::type_matches(ty, &event.event_type))
+        {
+            return false;
+        }
+        if matches!(&self.level, Some(level) if level != &event.level) {
+            return false;
+        }
+        if self.from_height.is_some() || self.to_height.is_some() {
+            let height = event.get("height").and_then(|h| h.parse::<u64>().ok());
+            match height {
+                Some(height) => {
+                    if self.from_height.map(|from| height < from).unwrap_or(false) {
+                        return false;
+                    }
+                    if self.to_height.map(|to| height > to).unwrap_or(false) {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+        self.attrs.iter().all(|pred| pred.matches(event))
+    }
+
+    /// Render this filter as a Tendermint subscription query string so the
+    /// filtering can be pushed server-side, e.g.
+    /// `tm.event='Tx' AND applied EXISTS AND hash='...'`. Attribute keys are
+    /// rendered as given, without an event-type prefix.
+    pub fn to_query_string(&self) -> String {
+        let mut clauses = Vec::new();
+        match self.level {
+            Some(EventLevel::Block) => {
+                clauses.push("tm.event='NewBlock'".to_string())
+            }
+            Some(EventLevel::Tx) => clauses.push("tm.event='Tx'".to_string()),
+            None => {}
+        }
+        for event_type in &self.event_types {
+            match event_type {
+                EventType::Ibc(prefix) => {
+                    clauses.push(format!("message.action CONTAINS '{}'", prefix))
+                }
+                other => clauses.push(format!("{} EXISTS", other)),
+            }
+        }
+        let height_key = match self.level {
+            Some(EventLevel::Block) => "block.height",
+            Some(EventLevel::Tx) | None => "tx.height",
+        };
+        if let Some(height) = self.from_height {
+            clauses.push(format!("{}>={}", height_key, height));
+        }
+        if let Some(height) = self.to_height {
+            clauses.push(format!("{}<={}", height_key, height));
+        }
+        for pred in &self.attrs {
+            match pred {
+                AttrPredicate::Eq(key, value) => {
+                    clauses.push(format!("{}='{}'", key, value))
+                }
+                AttrPredicate::Present(key) => {
+                    clauses.push(format!("{} EXISTS", key))
+                }
+                AttrPredicate::Contains(key, needle) => {
+                    clauses.push(format!("{} CONTAINS '{}'", key, needle))
+                }
+            }
+        }
+        clauses.join(" AND ")
+    }
+
+    fn type_matches(expected: &EventType, actual: &EventType) -> bool {
+        match (expected, actual) {
+            (EventType::Ibc(prefix), EventType::Ibc(actual)) => {
+                actual.starts_with(prefix.as_str())
+            }
+            (expected, actual) => expected == actual,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn event(event_type: EventType, level: EventLevel, attrs: &[(&str, &str)]) -> Event {
+        Event {
+            event_type,
+            level,
+            standard: super::super::EVENT_STANDARD.to_string(),
+            version: 1,
+            attributes: attrs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn matches_by_event_type_and_height_range() {
+        let filter = EventFilter::new()
+            .with_event_type(EventType::Applied)
+            .from_height(10)
+            .to_height(20);
+
+        let in_range = event(
+            EventType::Applied,
+            EventLevel::Tx,
+            &[("height", "15")],
+        );
+        assert!(filter.matches(&in_range));
+
+        let out_of_range = event(
+            EventType::Applied,
+            EventLevel::Tx,
+            &[("height", "25")],
+        );
+        assert!(!filter.matches(&out_of_range));
+
+        let wrong_type = event(
+            EventType::Accepted,
+            EventLevel::Tx,
+            &[("height", "15")],
+        );
+        assert!(!filter.matches(&wrong_type));
+    }
+
+    #[test]
+    fn ibc_event_type_matches_by_prefix() {
+        let filter = EventFilter::new()
+            .with_event_type(EventType::Ibc("send_packet".to_string()));
+        let matching = event(
+            EventType::Ibc("send_packet_acknowledgement".to_string()),
+            EventLevel::Tx,
+            &[],
+        );
+        assert!(filter.matches(&matching));
+    }
+
+    #[test]
+    fn attr_predicates_match() {
+        let filter = EventFilter::new()
+            .with_attr_eq("hash", "deadbeef")
+            .with_attr_contains("log", "success");
+        let matching = event(
+            EventType::Applied,
+            EventLevel::Tx,
+            &[("hash", "deadbeef"), ("log", "tx was a success")],
+        );
+        assert!(filter.matches(&matching));
+
+        let wrong_hash = event(
+            EventType::Applied,
+            EventLevel::Tx,
+            &[("hash", "cafebabe"), ("log", "tx was a success")],
+        );
+        assert!(!filter.matches(&wrong_hash));
+    }
+
+    #[test]
+    fn to_query_string_uses_consistent_exists_syntax() {
+        let filter = EventFilter::new()
+            .with_level(EventLevel::Tx)
+            .with_event_type(EventType::Applied)
+            .with_attr_eq("hash", "deadbeef")
+            .with_attr_present("log");
+        assert_eq!(
+            filter.to_query_string(),
+            "tm.event='Tx' AND applied EXISTS AND hash='deadbeef' AND log EXISTS"
+        );
+    }
+
+    #[test]
+    fn to_query_string_uses_block_height_for_block_level() {
+        let filter = EventFilter::new()
+            .with_level(EventLevel::Block)
+            .with_event_type(EventType::Proposal)
+            .from_height(10)
+            .to_height(20);
+        assert_eq!(
+            filter.to_query_string(),
+            "tm.event='NewBlock' AND proposal EXISTS AND block.height>=10 AND block.height<=20"
+        );
+    }
+}